@@ -3,26 +3,85 @@ use serde::{Serialize, Deserialize};
 use display_json::DisplayAsJsonPretty;
 use byte_unit::{Byte, AdjustedByte};
 
-/// Represents compute resources (CPU and Memory)
+/// Represents compute resources (CPU, Memory, and optionally Storage)
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Debug, DisplayAsJsonPretty)]
 pub struct Resources {
     pub memory: AdjustedByte,
     pub cpus: i64,
-    pub vcpus: Option<i64>
+    pub vcpus: Option<i64>,
+    /// Storage capacity, e.g. a guest's disk size or a node's local/attached storage capacity
+    pub storage: Option<AdjustedByte>,
+    /// Storage IOPS
+    pub iops: Option<i64>
 }
 
 fn adjusted_from_bytes(bytes: u128) -> AdjustedByte {
     Byte::from_bytes(bytes).get_appropriate_unit(false)
 }
 
+/// Represents a dimension of [`Resources`] going negative during a subtraction, i.e. an
+/// over-committed node or cluster definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceError {
+    /// Memory demand exceeds available memory; carries both amounts
+    MemoryUnderflow { available: AdjustedByte, required: AdjustedByte },
+    /// CPU demand exceeds available CPUs; carries both core counts
+    CpuUnderflow { available: i64, required: i64 },
+    /// vCPU demand exceeds available vCPUs; carries both vCPU counts
+    VcpuUnderflow { available: i64, required: i64 },
+    /// Storage demand exceeds available storage; carries both amounts
+    StorageUnderflow { available: AdjustedByte, required: AdjustedByte },
+    /// IOPS demand exceeds available IOPS; carries both IOPS counts
+    IopsUnderflow { available: i64, required: i64 },
+}
+
+impl std::fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResourceError::MemoryUnderflow { available, required } =>
+                write!(f, "reserves {} but capacity is only {}", required, available),
+            ResourceError::CpuUnderflow { available, required } =>
+                write!(f, "reserves {} CPUs but capacity is only {}", required, available),
+            ResourceError::VcpuUnderflow { available, required } =>
+                write!(f, "reserves {} vCPUs but capacity is only {}", required, available),
+            ResourceError::StorageUnderflow { available, required } =>
+                write!(f, "reserves {} storage but capacity is only {}", required, available),
+            ResourceError::IopsUnderflow { available, required } =>
+                write!(f, "reserves {} IOPS but capacity is only {}", required, available),
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
 /// Simplify working with Resources
+fn add_storage(a: Option<AdjustedByte>, b: Option<AdjustedByte>) -> Option<AdjustedByte> {
+    match (a, b) {
+        (Some(v), Some(rv)) => Some(adjusted_from_bytes(v.get_byte().get_bytes() + rv.get_byte().get_bytes())),
+        (Some(v), None) => Some(v),
+        (None, Some(rv)) => Some(rv),
+        (None, None) => None
+    }
+}
+
+fn sub_storage(a: Option<AdjustedByte>, b: Option<AdjustedByte>) -> Option<AdjustedByte> {
+    match (a, b) {
+        (Some(v), Some(rv)) => Some(adjusted_from_bytes(v.get_byte().get_bytes() - rv.get_byte().get_bytes())),
+        (Some(v), None) => Some(v),
+        (None, Some(rv)) => Some(rv),
+        (None, None) => None
+    }
+}
+
 impl<'a, 'b> ops::Add<&'b Resources> for &'a Resources {
     type Output = Resources;
     fn add(self, _rhs: &'b Resources) -> Resources {
         Resources {
             memory: adjusted_from_bytes(self.memory.get_byte().get_bytes() + _rhs.memory.get_byte().get_bytes()),
             cpus: self.cpus + _rhs.cpus,
-            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v + rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
+            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v + rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None},
+            storage: add_storage(self.storage, _rhs.storage),
+            iops: match (self.iops, _rhs.iops) { (Some(v), Some(rv)) => Some(v + rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
         }
     }
 }
@@ -32,7 +91,9 @@ impl<'a, 'b> ops::Sub<&'b Resources> for &'a Resources {
         Resources {
             memory: adjusted_from_bytes(self.memory.get_byte().get_bytes() - _rhs.memory.get_byte().get_bytes()),
             cpus: self.cpus - _rhs.cpus,
-            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
+            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None},
+            storage: sub_storage(self.storage, _rhs.storage),
+            iops: match (self.iops, _rhs.iops) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
         }
     }
 }
@@ -42,7 +103,9 @@ impl<'b> ops::Sub<&'b Resources> for Resources {
         Resources {
             memory: adjusted_from_bytes(self.memory.get_byte().get_bytes() - _rhs.memory.get_byte().get_bytes()),
             cpus: self.cpus - _rhs.cpus,
-            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
+            vcpus: match (self.vcpus, _rhs.vcpus) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None},
+            storage: sub_storage(self.storage, _rhs.storage),
+            iops: match (self.iops, _rhs.iops) { (Some(v), Some(rv)) => Some(v - rv), (Some(v), None) => Some(v), (None, Some(rv)) => Some(rv), (None, None) => None}
         }
     }
 }
@@ -52,7 +115,9 @@ impl<'b> ops::Mul<u64> for Resources {
         Resources {
             memory: adjusted_from_bytes(self.memory.get_byte().get_bytes() * _rhs as u128),
             cpus: self.cpus * _rhs as i64,
-            vcpus: match self.vcpus { Some(v) => Some(v * _rhs as i64), _ => None }
+            vcpus: match self.vcpus { Some(v) => Some(v * _rhs as i64), _ => None },
+            storage: match self.storage { Some(s) => Some(adjusted_from_bytes(s.get_byte().get_bytes() * _rhs as u128)), None => None },
+            iops: match self.iops { Some(v) => Some(v * _rhs as i64), _ => None }
         }
     }
 }
@@ -64,8 +129,66 @@ impl<'b> ops::Mul<u64> for &Resources {
     }
 }
 
+impl Resources {
+    /// Subtracts `rhs` from `self`, checking each dimension for underflow instead of panicking
+    /// (debug builds) or silently wrapping (release builds)
+    ///
+    /// This is the bounds-checked counterpart to the `Sub` impls above, to be used wherever an
+    /// over-committed node or cluster definition (consumed + overhead exceeding capacity) must be
+    /// reported as a diagnostic rather than crash the estimator.
+    pub fn checked_sub(&self, rhs: &Resources) -> Result<Resources, ResourceError> {
+        let avail_mem = self.memory.get_byte().get_bytes();
+        let req_mem = rhs.memory.get_byte().get_bytes();
+        if avail_mem < req_mem {
+            return Err(ResourceError::MemoryUnderflow { available: self.memory, required: rhs.memory });
+        }
+        if self.cpus < rhs.cpus {
+            return Err(ResourceError::CpuUnderflow { available: self.cpus, required: rhs.cpus });
+        }
+        let vcpus = match (self.vcpus, rhs.vcpus) {
+            (Some(v), Some(rv)) => {
+                if v < rv { return Err(ResourceError::VcpuUnderflow { available: v, required: rv }); }
+                Some(v - rv)
+            },
+            (Some(v), None) => Some(v),
+            (None, Some(rv)) => Some(rv),
+            (None, None) => None
+        };
+        let storage = match (self.storage, rhs.storage) {
+            (Some(s), Some(rs)) => {
+                let avail_storage = s.get_byte().get_bytes();
+                let req_storage = rs.get_byte().get_bytes();
+                if avail_storage < req_storage {
+                    return Err(ResourceError::StorageUnderflow { available: s, required: rs });
+                }
+                Some(adjusted_from_bytes(avail_storage - req_storage))
+            },
+            (Some(s), None) => Some(s),
+            (None, Some(rs)) => Some(rs),
+            (None, None) => None
+        };
+        let iops = match (self.iops, rhs.iops) {
+            (Some(v), Some(rv)) => {
+                if v < rv { return Err(ResourceError::IopsUnderflow { available: v, required: rv }); }
+                Some(v - rv)
+            },
+            (Some(v), None) => Some(v),
+            (None, Some(rv)) => Some(rv),
+            (None, None) => None
+        };
+
+        Ok(Resources {
+            memory: adjusted_from_bytes(avail_mem - req_mem),
+            cpus: self.cpus - rhs.cpus,
+            vcpus,
+            storage,
+            iops
+        })
+    }
+}
+
 /// Represents a group of workload VMs - as a desired target or available capacity
-#[derive(Serialize, Deserialize, DisplayAsJsonPretty)]
+#[derive(Clone, Serialize, Deserialize, DisplayAsJsonPretty)]
 pub struct Workloads {
     /// How many VMs are compsing this workload
     pub vm_count: u64,
@@ -80,7 +203,7 @@ impl Workloads {
 }
 
 /// Represents the instance type (size) of a workload
-#[derive(Serialize, Deserialize, DisplayAsJsonPretty)]
+#[derive(Clone, Serialize, Deserialize, DisplayAsJsonPretty)]
 pub struct InstanceType {
     /// The name of the instanceType
     pub name: String,
@@ -90,7 +213,11 @@ pub struct InstanceType {
     pub consumed_by_system: Resources,
     /// Resources reserved for caches, buffers, and workload depend overheads (difficult to
     /// predict)
-    pub reserved_for_overhead: Resources
+    pub reserved_for_overhead: Resources,
+    /// If set, this instanceType is charged against physical cores rather than the over-committed
+    /// logical/vCPU pool, e.g. for latency-sensitive workloads requiring dedicated CPUs
+    #[serde(default)]
+    pub no_overcommit: bool
 }
 
 impl InstanceType {
@@ -118,14 +245,51 @@ impl InstanceType {
         let avail = &resources.available_to_workloads;
         let req = self.resource_footprint();
         let fit_into_memory = (avail.memory.get_byte().get_bytes() as f64 / req.memory.get_byte().get_bytes() as f64).floor() as u64;
-        let fit_into_cpu = (avail.cpus as f64 / req.cpus as f64).floor() as u64;
-        if fit_into_memory < fit_into_cpu {
+
+        // A `no_overcommit` instanceType (e.g. for latency-sensitive workloads) is charged
+        // against physical cores; otherwise its `cpus` demand is charged against the
+        // over-committed logical/vCPU pool (instanceTypes never populate a separate `vcpus`
+        // demand of their own).
+        let (fit_into_cpu, cpu_label) = if self.no_overcommit {
+            ((avail.cpus as f64 / req.cpus as f64).floor() as u64, "physical CPU")
+        } else {
+            match avail.vcpus {
+                Some(avail_vcpus) if req.cpus > 0 =>
+                    ((avail_vcpus as f64 / req.cpus as f64).floor() as u64, "vCPU"),
+                _ => ((avail.cpus as f64 / req.cpus as f64).floor() as u64, "CPU")
+            }
+        };
+
+        let (mut fit_count, mut reason) = if fit_into_memory < fit_into_cpu {
             (fit_into_memory, "Memory constraint".to_string())
         } else if fit_into_cpu < fit_into_memory {
-            (fit_into_cpu, "CPU constraint".to_string())
+            (fit_into_cpu, format!("{} constraint", cpu_label))
         } else {
             (fit_into_cpu, "CPU and memory constratint".to_string())
+        };
+
+        if let (Some(avail_storage), Some(req_storage)) = (avail.storage, req.storage) {
+            let req_storage_bytes = req_storage.get_byte().get_bytes();
+            if req_storage_bytes > 0 {
+                let fit_into_storage = (avail_storage.get_byte().get_bytes() as f64 / req_storage_bytes as f64).floor() as u64;
+                if fit_into_storage < fit_count {
+                    fit_count = fit_into_storage;
+                    reason = "Constrained by storage".to_string();
+                }
+            }
         }
+
+        if let (Some(avail_iops), Some(req_iops)) = (avail.iops, req.iops) {
+            if req_iops > 0 {
+                let fit_into_iops = (avail_iops as f64 / req_iops as f64).floor() as u64;
+                if fit_into_iops < fit_count {
+                    fit_count = fit_into_iops;
+                    reason = "Constrained by IOPS".to_string();
+                }
+            }
+        }
+
+        (fit_count, reason)
     }
 
 }
@@ -145,8 +309,13 @@ pub struct Node {
 }
 
 impl Node {
-    fn compute_allocatable(&self) -> Resources {
-        &self.capacity - &self.consumed_by_system - &self.reserved_for_overhead
+    /// Computes the resources left over for workloads once system consumption and overhead
+    /// reservations are subtracted from capacity
+    ///
+    /// Returns a [`ResourceError`] naming the dimension (and by how much) the node is
+    /// over-committed in, rather than underflowing.
+    fn compute_allocatable(&self) -> Result<Resources, ResourceError> {
+        self.capacity.checked_sub(&self.consumed_by_system)?.checked_sub(&self.reserved_for_overhead)
     }
 }
 
@@ -171,8 +340,43 @@ pub struct ClusterTopology {
     pub worker_node: Node,
     /// Ratio of CPU over-commitment, i.e. 1:10 = 1/10 = 0.1
     pub cpu_over_commit_ratio: f32,
+    /// Number of availability zones (fault domains) worker nodes are spread across
+    #[serde(default = "default_zones")]
+    pub zones: u64,
+    /// If set, the cluster must still fit the workload after losing the single largest zone
+    #[serde(default)]
+    pub zone_redundancy: bool,
+    /// Replication factor of the storage layer, i.e. 3 means raw capacity is divided by three to
+    /// yield usable capacity
+    #[serde(default = "default_storage_replica_factor")]
+    pub storage_replica_factor: u64,
+    /// Number of logical threads exposed per physical core (SMT/hyperthreading), typically 2
+    #[serde(default = "default_threads_per_core")]
+    pub threads_per_core: u64,
+    /// Number of worker nodes that may be simultaneously unavailable for upgrades/drains; the
+    /// cluster is sized so the workload still fits with this many worker nodes removed
+    #[serde(default = "default_maintenance_headroom")]
+    pub maintenance_headroom: u64,
+    /// Optional fraction (0.0 disables) of per-node memory reserved to receive live-migrated
+    /// guests while a node is being drained
+    #[serde(default)]
+    pub migration_reserve_fraction: f32,
 }
 
+/// A topology predating the zone series had exactly one zone
+fn default_zones() -> u64 { 1 }
+
+/// A topology predating the storage series had no replication (raw == usable capacity)
+fn default_storage_replica_factor() -> u64 { 1 }
+
+/// A topology predating the SMT series had no distinction between physical and logical cores
+fn default_threads_per_core() -> u64 { 1 }
+
+/// A topology predating the headroom series always implicitly reserved one node of capacity for
+/// maintenance/failure, so an un-migrated config must keep that guarantee rather than silently
+/// dropping to zero headroom
+fn default_maintenance_headroom() -> u64 { 1 }
+
 /// Represents a Cluster
 #[derive(Serialize, Deserialize, DisplayAsJsonPretty)]
 pub struct Cluster {
@@ -193,26 +397,184 @@ impl Cluster {
             worker_node_count: 0
         };
 
+        // A structurally over-committed worker node (`consumed_by_system + reserved_for_overhead`
+        // exceeding `capacity`) makes `compute_allocatable` fail the same way no matter how many
+        // worker nodes are added -- aggregate allocatable capacity is pinned at zero, so the
+        // sizing loop below could never terminate. Bail out with the diagnostic up front instead
+        // of looping forever.
+        if let Err(e) = cluster.topology.worker_node.compute_allocatable() {
+            reasons.push(format!("Worker node is over-committed ({}); no number of worker nodes can make the workload fit", e));
+            cluster.worker_node_count = 1;
+            return ReasonedResult { result: cluster, reasons };
+        }
+
+        // Find the minimum worker node count the workload fits into (tolerating a zone failure
+        // too, if zone redundancy is requested)
+        loop {
+            cluster.worker_node_count += 1;
+            let resources_to_check = if cluster.topology.zone_redundancy {
+                cluster.effective_resources()
+            } else {
+                cluster.resources()
+            };
+            for reason in resources_to_check.reasons {
+                if !reasons.contains(&reason) { reasons.push(reason); }
+            }
+            if workloads.can_fit_into(&resources_to_check.result).result { break }
+        }
+
+        // Keep growing the cluster until the workload still fits with `maintenance_headroom`
+        // worker nodes simultaneously unavailable, e.g. for a rolling upgrade drain
         loop {
-            let fit_into_cluster = workloads.can_fit_into(&cluster.resources());
-            // We always add one more node in order to have capacity for LM
+            let headroom_resources = cluster.resources_after_losing_workers(cluster.topology.maintenance_headroom);
+            for reason in headroom_resources.reasons {
+                if !reasons.contains(&reason) { reasons.push(reason); }
+            }
+            if workloads.can_fit_into(&headroom_resources.result).result { break }
             cluster.worker_node_count += 1;
-            if fit_into_cluster.result == true { break }
         }
-        reasons.push("One additional node is getting included on top of the required ones, in order to enable full-node drains required for updating the cluster".to_string());
+        reasons.push(format!("{} worker node(s) of maintenance headroom are reserved so the workload still fits while that many nodes are drained simultaneously", cluster.topology.maintenance_headroom));
+
+        if cluster.topology.zones > 1 {
+            let per_zone = cluster.worker_nodes_per_zone();
+            reasons.push(format!("Worker nodes are distributed across {} zones: {:?}", cluster.topology.zones, per_zone));
+            if cluster.topology.zone_redundancy {
+                reasons.push(format!("Zone redundancy requested: reserving the capacity of the largest zone ({} worker node(s)) in order to tolerate a single zone failure", cluster.largest_zone_worker_node_count()));
+            }
+        }
 
         ReasonedResult {
             result: cluster,
-            reasons: reasons
+            reasons
         }
     }
+
+    /// Distributes the worker nodes across the configured zones, assigning node `i` to zone `i
+    /// % zones`
+    ///
+    /// Returns a vector of per-zone worker node counts, indexed by zone.
+    pub fn worker_nodes_per_zone(&self) -> Vec<u64> {
+        let zones = self.topology.zones.max(1);
+        let mut counts = vec![0u64; zones as usize];
+        for i in 0..self.worker_node_count {
+            counts[(i % zones) as usize] += 1;
+        }
+        counts
+    }
+
+    /// The number of worker nodes held by the single most populated zone
+    ///
+    /// This is the capacity that is lost in the worst-case single zone failure.
+    pub fn largest_zone_worker_node_count(&self) -> u64 {
+        self.worker_nodes_per_zone().into_iter().max().unwrap_or(0)
+    }
+
+    /// Resources of the cluster after the single largest zone has failed
+    ///
+    /// When `topology.zone_redundancy` is set, this subtracts the capacity held by the largest
+    /// zone from the cluster's resources, so that sizing can require the workload to still fit
+    /// while one zone (rack/AZ) is down. Without zone redundancy, or with a single zone, this is
+    /// identical to [`Cluster::resources`].
+    pub fn effective_resources(&self) -> ReasonedResult<ClusterResources> {
+        if !self.topology.zone_redundancy || self.topology.zones <= 1 {
+            return self.resources();
+        }
+        self.resources_after_losing_workers(self.largest_zone_worker_node_count())
+    }
+
+    /// Resources of the cluster after `lost_node_count` worker nodes simultaneously become
+    /// unavailable, e.g. due to a zone outage or a maintenance drain
+    ///
+    /// When `migration_reserve_fraction` is set, `self.resources()` below has already subtracted
+    /// that reserve (sized against the *full* `worker_node_count`) from `available_to_workloads`,
+    /// and the lost nodes' raw allocatable capacity subtracted further down doesn't know about
+    /// that reserve and isn't rescaled for it. The net effect is that the lost nodes' share of the
+    /// reserve is still charged against the remaining nodes, which only makes the resulting estimate more
+    /// conservative (a somewhat larger cluster), never an unsafe smaller one -- left as-is rather
+    /// than threading the remaining-node count through the reserve calculation for a low-severity
+    /// over-estimate.
+    fn resources_after_losing_workers(&self, lost_node_count: u64) -> ReasonedResult<ClusterResources> {
+        let reasoned = self.resources();
+        let mut reasons = reasoned.reasons;
+        let resources = reasoned.result;
+
+        // `lost_node_count` (e.g. `maintenance_headroom`) is configured independently of the
+        // cluster's current `worker_node_count`, which is routinely smaller while the sizing loop
+        // is still growing the cluster; clamp it so the subtractions below can never underflow.
+        let lost_node_count = lost_node_count.min(self.worker_node_count);
+
+        if lost_node_count == 0 {
+            return ReasonedResult { result: resources, reasons };
+        }
+
+        let worker_node = &self.topology.worker_node;
+
+        let mut lost_allocatable = match worker_node.compute_allocatable() {
+            Ok(allocatable) => allocatable * lost_node_count,
+            Err(e) => {
+                reasons.push(format!("Worker node is over-committed ({}), treating the lost node(s)' workload capacity as zero", e));
+                Resources { memory: adjusted_from_bytes(0), cpus: 0, vcpus: None, storage: None, iops: None }
+            }
+        };
+
+        // Usable storage is already replica-divided in `resources()`, so the lost nodes' share of
+        // it must be computed the same way rather than from their raw per-node allocatable.
+        lost_allocatable.storage = worker_node.capacity.storage.map(|raw_storage| {
+            let replica_factor = self.topology.storage_replica_factor.max(1);
+            adjusted_from_bytes(raw_storage.get_byte().get_bytes() * lost_node_count as u128 / replica_factor as u128)
+        });
+
+        let available_to_workloads = match resources.available_to_workloads.checked_sub(&lost_allocatable) {
+            Ok(mut available) => {
+                let threads_per_core = self.topology.threads_per_core.max(1);
+                available.vcpus = Some(((available.cpus * threads_per_core as i64) as f32 * (1.0 / self.topology.cpu_over_commit_ratio)) as i64);
+                available
+            },
+            Err(e) => {
+                reasons.push(format!("Losing {} worker node(s) would exceed available workload capacity ({})", lost_node_count, e));
+                Resources { memory: adjusted_from_bytes(0), cpus: 0, vcpus: Some(0), storage: None, iops: Some(0) }
+            }
+        };
+
+        let consumed_by_system = match resources.consumed_by_system.checked_sub(&(worker_node.consumed_by_system * lost_node_count)) {
+            Ok(consumed) => consumed,
+            Err(e) => {
+                reasons.push(format!("Losing {} worker node(s) would exceed consumed-by-system capacity ({})", lost_node_count, e));
+                Resources { memory: adjusted_from_bytes(0), cpus: 0, vcpus: None, storage: None, iops: None }
+            }
+        };
+
+        let reserved_for_overhead = match resources.reserved_for_overhead.checked_sub(&(worker_node.reserved_for_overhead * lost_node_count)) {
+            Ok(reserved) => reserved,
+            Err(e) => {
+                reasons.push(format!("Losing {} worker node(s) would exceed reserved-for-overhead capacity ({})", lost_node_count, e));
+                Resources { memory: adjusted_from_bytes(0), cpus: 0, vcpus: None, storage: None, iops: None }
+            }
+        };
+
+        let cr = ClusterResources {
+            consumed_by_system,
+            reserved_for_overhead,
+            available_to_workloads
+        };
+
+        ReasonedResult { result: cr, reasons }
+    }
+
     /// Compute the cluster resources of this cluster
-    pub fn resources(&self) -> ClusterResources {
+    pub fn resources(&self) -> ReasonedResult<ClusterResources> {
+        let mut reasons = Vec::new();
         let worker_node = &self.topology.worker_node;
 
         let mut consumed = worker_node.consumed_by_system * self.worker_node_count;
         let mut overhead = worker_node.reserved_for_overhead * self.worker_node_count;
-        let mut workload = worker_node.compute_allocatable() * self.worker_node_count;
+        let mut workload = match worker_node.compute_allocatable() {
+            Ok(allocatable) => allocatable * self.worker_node_count,
+            Err(e) => {
+                reasons.push(format!("Worker node is over-committed ({}), using zero allocatable capacity", e));
+                Resources { memory: adjusted_from_bytes(0), cpus: 0, vcpus: None, storage: None, iops: None }
+            }
+        };
 
         if self.topology.schedulable_control_plane {
             //rs.push(Reason("More capacity due to schedulable control plane nodes".to_string()));
@@ -222,23 +584,233 @@ impl Cluster {
             let ctl_capacity = ctl_node.capacity * ctl_node_count;
             let ctl_consumed = ctl_node.consumed_by_system * ctl_node_count;
             let ctl_overhead = ctl_node.reserved_for_overhead * ctl_node_count;
-            let ctl_workload = &ctl_capacity - &ctl_consumed - &ctl_overhead;
+            match ctl_capacity.checked_sub(&ctl_consumed).and_then(|r| r.checked_sub(&ctl_overhead)) {
+                Ok(mut ctl_workload) => {
+                    // The replicated-storage model below only replicates worker node storage, so
+                    // control-plane storage must not be folded into `workload.storage` here -- it
+                    // would otherwise be silently discarded a few lines down when that model
+                    // overwrites `workload.storage` wholesale.
+                    ctl_workload.storage = None;
+                    consumed = &consumed + &ctl_consumed;
+                    overhead = &overhead + &ctl_overhead;
+                    workload = &workload + &ctl_workload;
+                },
+                Err(e) => {
+                    reasons.push(format!("Control plane node is over-committed ({}), excluding its capacity", e));
+                }
+            }
+        }
 
-            consumed = &consumed + &ctl_consumed;
-            overhead = &overhead + &ctl_overhead;
-            workload = &workload + &ctl_workload;
+        if self.topology.migration_reserve_fraction > 0.0 {
+            let per_node_memory_bytes = worker_node.capacity.memory.get_byte().get_bytes();
+            let reserve_bytes = (per_node_memory_bytes as f64 * self.topology.migration_reserve_fraction as f64 * self.worker_node_count as f64) as u128;
+            let migration_reserve = Resources { memory: adjusted_from_bytes(reserve_bytes), cpus: 0, vcpus: None, storage: None, iops: None };
+            match workload.checked_sub(&migration_reserve) {
+                Ok(remaining) => {
+                    reasons.push(format!("{:.0}% of per-node memory ({} in total across worker nodes) is reserved to receive live-migrated guests during a drain", self.topology.migration_reserve_fraction * 100.0, migration_reserve.memory));
+                    workload = remaining;
+                    overhead = &overhead + &migration_reserve;
+                },
+                Err(e) => {
+                    reasons.push(format!("Live-migration memory reserve could not be subtracted from workload memory ({}), no memory is available to workloads", e));
+                    workload.memory = adjusted_from_bytes(0);
+                }
+            }
         }
 
-        workload.vcpus = Some((workload.cpus as f32 * (1.0 / self.topology.cpu_over_commit_ratio)) as i64);
+        // `workload.cpus` are physical cores; each one exposes `threads_per_core` logical threads
+        // (SMT/hyperthreading), and the logical pool is then over-committed into vCPUs.
+        let threads_per_core = self.topology.threads_per_core.max(1);
+        let logical_cpus = workload.cpus * threads_per_core as i64;
+        workload.vcpus = Some((logical_cpus as f32 * (1.0 / self.topology.cpu_over_commit_ratio)) as i64);
+        reasons.push(format!("{} physical core(s) expose {} logical CPU(s) ({} threads/core), over-committed to {} vCPU(s)", workload.cpus, logical_cpus, threads_per_core, workload.vcpus.unwrap()));
+
+        // A replicated storage layer (e.g. Ceph/ODF) turns raw per-node disk into usable capacity
+        // by a replication factor, rather than by the consumed/overhead subtraction used above for
+        // memory and CPU.
+        if let Some(raw_storage) = worker_node.capacity.storage {
+            let replica_factor = self.topology.storage_replica_factor.max(1);
+            let raw_total_bytes = raw_storage.get_byte().get_bytes() * self.worker_node_count as u128;
+            workload.storage = Some(adjusted_from_bytes(raw_total_bytes / replica_factor as u128));
+            reasons.push(format!("Usable storage is raw worker node storage divided by the replication factor ({}x)", replica_factor));
+        }
 
-        ClusterResources {
+        let cr = ClusterResources {
             consumed_by_system: consumed,
             reserved_for_overhead: overhead,
             available_to_workloads: workload
+        };
+
+        ReasonedResult { result: cr, reasons }
+    }
+
+    /// Places the VM instances of `workloads` onto concrete nodes using first-fit-decreasing
+    /// bin-packing
+    ///
+    /// `Workloads::can_fit_into` and `InstanceType::how_many_fit_into` only compare aggregate
+    /// totals, which overestimates capacity because a VM cannot straddle two nodes. This builds
+    /// one remaining-capacity bucket per schedulable node (workers, plus control plane nodes when
+    /// `schedulable_control_plane` is set), sorts VM instances by memory footprint descending, and
+    /// places each into the first bucket with enough memory and CPU (physical or over-committed
+    /// vCPU, matching `can_fit_into`'s no_overcommit handling) left, decrementing it. Storage and
+    /// IOPS are shared, replicated cluster-wide pools (not per-node), so they are checked and
+    /// decremented against the same aggregate totals `can_fit_into` uses, rather than per bucket.
+    /// VMs that cannot be placed are counted as unplaced; the reason tells fragmentation (aggregate
+    /// headroom exists) apart from a cluster whose aggregate capacity is too small.
+    pub fn place(&self, workloads: &Workloads) -> ReasonedResult<Placement> {
+        let mut reasons = Vec::new();
+        let threads_per_core = self.topology.threads_per_core.max(1);
+
+        let worker_node = &self.topology.worker_node;
+        let mut buckets: Vec<Resources> = Vec::new();
+
+        // `Cluster::resources()` permanently carves the live-migration reserve out of
+        // `available_to_workloads.memory`; per-worker-node buckets must lose the same share of
+        // their memory so a VM can't be placed into capacity that's reserved for incoming
+        // migrations during a drain.
+        let migration_reserve_bytes = if self.topology.migration_reserve_fraction > 0.0 {
+            (worker_node.capacity.memory.get_byte().get_bytes() as f64 * self.topology.migration_reserve_fraction as f64) as u128
+        } else {
+            0
+        };
+        if migration_reserve_bytes > 0 {
+            reasons.push(format!("{:.0}% of per-node memory ({}) is reserved to receive live-migrated guests during a drain", self.topology.migration_reserve_fraction * 100.0, adjusted_from_bytes(migration_reserve_bytes)));
+        }
+
+        for _ in 0..self.worker_node_count {
+            match worker_node.compute_allocatable() {
+                Ok(mut allocatable) => {
+                    let available_bytes = allocatable.memory.get_byte().get_bytes();
+                    allocatable.memory = adjusted_from_bytes(available_bytes.saturating_sub(migration_reserve_bytes));
+                    buckets.push(allocatable);
+                },
+                Err(e) => reasons.push(format!("Worker node is over-committed ({}), excluding it from placement", e))
+            }
+        }
+
+        if self.topology.schedulable_control_plane {
+            let ctl_node = &self.topology.control_plane_node;
+            for _ in 0..self.control_plane_node_count {
+                match ctl_node.compute_allocatable() {
+                    Ok(allocatable) => buckets.push(allocatable),
+                    Err(e) => reasons.push(format!("Control plane node is over-committed ({}), excluding it from placement", e))
+                }
+            }
+        }
+
+        for bucket in &mut buckets {
+            bucket.vcpus = Some(((bucket.cpus * threads_per_core as i64) as f32 * (1.0 / self.topology.cpu_over_commit_ratio)) as i64);
+        }
+
+        let cluster_resources = self.resources();
+        reasons.extend(cluster_resources.reasons);
+        let mut pool_storage = cluster_resources.result.available_to_workloads.storage;
+        let mut pool_iops = cluster_resources.result.available_to_workloads.iops;
+
+        let footprint = workloads.instance_type.resource_footprint();
+        let mut instances: Vec<Resources> = std::iter::repeat_n(footprint, workloads.vm_count as usize).collect();
+        instances.sort_by_key(|r| std::cmp::Reverse(r.memory.get_byte().get_bytes()));
+
+        let mut placed_per_node = vec![0u64; buckets.len()];
+        let mut unplaced_count = 0u64;
+        let mut blocked_by_memory = 0u64;
+        let mut blocked_by_cpu = 0u64;
+        let mut blocked_by_storage = 0u64;
+        let mut blocked_by_iops = 0u64;
+
+        for instance in &instances {
+            // Storage and IOPS are shared, cluster-wide pools (unlike memory/CPU, which are
+            // node-local), so an instance that the per-node buckets could place is still blocked
+            // if the shared pool is exhausted.
+            let fits_storage = match (pool_storage, instance.storage) {
+                (Some(avail), Some(req)) => avail.get_byte().get_bytes() >= req.get_byte().get_bytes(),
+                _ => true
+            };
+            let fits_iops = match (pool_iops, instance.iops) {
+                (Some(avail), Some(req)) => avail >= req,
+                _ => true
+            };
+            if !fits_storage || !fits_iops {
+                unplaced_count += 1;
+                if !fits_storage { blocked_by_storage += 1; }
+                if !fits_iops { blocked_by_iops += 1; }
+                continue;
+            }
+
+            let mut placed = false;
+            let mut any_memory_fits = false;
+            let mut any_cpu_fits = false;
+
+            for (i, bucket) in buckets.iter_mut().enumerate() {
+                let fits_memory = bucket.memory.get_byte().get_bytes() >= instance.memory.get_byte().get_bytes();
+                let fits_cpu = if workloads.instance_type.no_overcommit {
+                    bucket.cpus >= instance.cpus
+                } else {
+                    bucket.vcpus.unwrap_or(bucket.cpus) >= instance.cpus
+                };
+                any_memory_fits |= fits_memory;
+                any_cpu_fits |= fits_cpu;
+
+                if fits_memory && fits_cpu {
+                    bucket.memory = adjusted_from_bytes(bucket.memory.get_byte().get_bytes() - instance.memory.get_byte().get_bytes());
+                    if workloads.instance_type.no_overcommit {
+                        // A dedicated physical core is removed from both the physical pool and
+                        // the logical pool it was backing.
+                        bucket.cpus -= instance.cpus;
+                        bucket.vcpus = Some(bucket.vcpus.unwrap_or(0) - ((instance.cpus * threads_per_core as i64) as f32 * (1.0 / self.topology.cpu_over_commit_ratio)) as i64);
+                    } else {
+                        bucket.vcpus = Some(bucket.vcpus.unwrap_or(0) - instance.cpus);
+                    }
+                    placed_per_node[i] += 1;
+                    placed = true;
+                    break;
+                }
+            }
+
+            if !placed {
+                unplaced_count += 1;
+                if !any_memory_fits { blocked_by_memory += 1; }
+                if !any_cpu_fits { blocked_by_cpu += 1; }
+            } else {
+                pool_storage = match (pool_storage, instance.storage) {
+                    (Some(avail), Some(req)) => Some(adjusted_from_bytes(avail.get_byte().get_bytes() - req.get_byte().get_bytes())),
+                    _ => pool_storage
+                };
+                pool_iops = match (pool_iops, instance.iops) { (Some(v), Some(rv)) => Some(v - rv), _ => pool_iops };
+            }
+        }
+
+        reasons.push(format!("Placed {} of {} VM instances across {} node(s): {:?}", instances.len() as u64 - unplaced_count, instances.len(), buckets.len(), placed_per_node));
+        if unplaced_count > 0 {
+            // With a single node there is nothing to fragment across -- the node itself is too
+            // small for the footprint, which is a different failure than true multi-node
+            // fragmentation and shouldn't be reported as one.
+            let cause = if !workloads.can_fit_into(&cluster_resources.result).result {
+                "insufficient aggregate capacity".to_string()
+            } else if buckets.len() <= 1 {
+                "no single node having enough room for the footprint despite aggregate headroom existing".to_string()
+            } else {
+                "fragmentation across nodes despite aggregate headroom existing".to_string()
+            };
+            reasons.push(format!("{} VM instance(s) could not be placed, due to {} ({} memory-constrained, {} CPU-constrained, {} storage-constrained, {} IOPS-constrained)", unplaced_count, cause, blocked_by_memory, blocked_by_cpu, blocked_by_storage, blocked_by_iops));
+        }
+
+        ReasonedResult {
+            result: Placement { placed_per_node, unplaced_count },
+            reasons
         }
     }
 }
 
+/// Represents the outcome of placing VM instances onto concrete cluster nodes (bin-packing)
+#[derive(Debug, Serialize, DisplayAsJsonPretty)]
+pub struct Placement {
+    /// How many VM instances were placed onto each schedulable node, indexed by node
+    pub placed_per_node: Vec<u64>,
+    /// How many VM instances could not be placed onto any node
+    pub unplaced_count: u64,
+}
+
 /// Represents a detailed view on the resource distribution in a Cluster
 #[derive(Serialize, DisplayAsJsonPretty)]
 pub struct ClusterResources {
@@ -273,20 +845,424 @@ impl Workloads {
         Resources {
             memory: adjusted_from_bytes(self.instance_type.guest.memory.get_byte().get_bytes() * c as u128),
             cpus: self.instance_type.guest.cpus * c as i64,
-            vcpus: match self.instance_type.guest.vcpus { Some(v) => Some(v * c as i64), None => None }
+            vcpus: self.instance_type.guest.vcpus.map(|v| v * c as i64),
+            storage: self.instance_type.guest.storage.map(|s| adjusted_from_bytes(s.get_byte().get_bytes() * c as u128)),
+            iops: self.instance_type.guest.iops.map(|v| v * c as i64)
         }
     }
 
     /// Determines if this workload fits into the given cluster resources
+    ///
+    /// This charges the full [`InstanceType::resource_footprint`] (guest + `consumed_by_system` +
+    /// `reserved_for_overhead`), the same demand [`Cluster::place`] and
+    /// [`InstanceType::how_many_fit_into`] check. It compares aggregate totals only, so a cluster
+    /// this reports as fitting the workload can still leave VMs unplaced in `place()` when free
+    /// capacity is fragmented across nodes.
     pub fn can_fit_into(&self, resources: &ClusterResources) -> ReasonedResult<bool> {
         let avail = &resources.available_to_workloads;
-        let req = self.required_resources();
+        let req = self.required_capacity();
         let avail_mem = avail.memory.get_byte().get_bytes();
         let req_mem = req.memory.get_byte().get_bytes();
 
         if avail_mem < req_mem { return ReasonedResult{result: false, reasons: vec!["Constrained by memory".to_string()]} };
-        if avail.cpus < req.cpus { return ReasonedResult{result: false, reasons: vec!["Constrained by pCPU".to_string()]} };
-        //if avail.vcpus < req.vcpus { return ReasoneResult(result: false, reasons: vec!["Constrained by vCPU"]) };
+
+        // A `no_overcommit` instanceType is charged against physical cores; otherwise its `cpus`
+        // demand is charged against the over-committed logical/vCPU pool (instanceTypes never
+        // populate a separate `vcpus` demand of their own).
+        if self.instance_type.no_overcommit {
+            if avail.cpus < req.cpus { return ReasonedResult{result: false, reasons: vec!["Constrained by physical CPU (no over-commit instanceType)".to_string()]} };
+        } else {
+            match avail.vcpus {
+                Some(avail_vcpus) if avail_vcpus < req.cpus =>
+                    return ReasonedResult{result: false, reasons: vec!["Constrained by vCPU".to_string()]},
+                Some(_) => (),
+                None => if avail.cpus < req.cpus { return ReasonedResult{result: false, reasons: vec!["Constrained by pCPU".to_string()]} }
+            }
+        }
+        if let (Some(avail_storage), Some(req_storage)) = (avail.storage, req.storage) {
+            if avail_storage.get_byte().get_bytes() < req_storage.get_byte().get_bytes() {
+                return ReasonedResult{result: false, reasons: vec!["Constrained by storage".to_string()]};
+            }
+        }
+        if let (Some(avail_iops), Some(req_iops)) = (avail.iops, req.iops) {
+            if avail_iops < req_iops {
+                return ReasonedResult{result: false, reasons: vec!["Constrained by IOPS".to_string()]};
+            }
+        }
         ReasonedResult{result: true, reasons: vec![]}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources(memory_gib: u64, cpus: i64) -> Resources {
+        Resources {
+            memory: adjusted_from_bytes(memory_gib as u128 * 1024 * 1024 * 1024),
+            cpus,
+            vcpus: None,
+            storage: None,
+            iops: None
+        }
+    }
+
+    #[test]
+    fn checked_sub_errors_on_memory_underflow() {
+        let available = resources(4, 8);
+        let required = resources(8, 2);
+        match available.checked_sub(&required) {
+            Err(ResourceError::MemoryUnderflow { .. }) => (),
+            other => panic!("expected a MemoryUnderflow, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn checked_sub_errors_on_cpu_underflow() {
+        let available = resources(8, 2);
+        let required = resources(4, 8);
+        match available.checked_sub(&required) {
+            Err(ResourceError::CpuUnderflow { available: 2, required: 8 }) => (),
+            other => panic!("expected a CpuUnderflow, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn checked_sub_succeeds_when_capacity_is_sufficient() {
+        let available = resources(8, 8);
+        let required = resources(4, 2);
+        let remaining = available.checked_sub(&required).expect("should fit");
+        assert_eq!(remaining.cpus, 6);
+        assert_eq!(remaining.memory.get_byte().get_bytes(), adjusted_from_bytes(4 * 1024 * 1024 * 1024).get_byte().get_bytes());
+    }
+
+    fn node(memory_gib: u64, cpus: i64) -> Node {
+        Node {
+            description: "test node".to_string(),
+            capacity: resources(memory_gib, cpus),
+            consumed_by_system: resources(0, 0),
+            reserved_for_overhead: resources(0, 0)
+        }
+    }
+
+    fn topology(maintenance_headroom: u64) -> ClusterTopology {
+        ClusterTopology {
+            schedulable_control_plane: false,
+            control_plane_node: node(16, 4),
+            worker_node: node(64, 16),
+            cpu_over_commit_ratio: 1.0,
+            zones: 1,
+            zone_redundancy: false,
+            storage_replica_factor: 1,
+            threads_per_core: 1,
+            maintenance_headroom,
+            migration_reserve_fraction: 0.0
+        }
+    }
+
+    fn small_workload() -> Workloads {
+        Workloads {
+            vm_count: 2,
+            instance_type: InstanceType {
+                name: "u1.small".to_string(),
+                guest: resources(4, 2),
+                consumed_by_system: resources(0, 0),
+                reserved_for_overhead: resources(0, 0),
+                no_overcommit: false
+            }
+        }
+    }
+
+    // A 2-VM workload only needs a couple of worker nodes to fit, which is routinely smaller
+    // than an independently configured maintenance_headroom (here 5) -- this must not panic.
+    #[test]
+    fn sizing_with_headroom_larger_than_baseline_worker_count_does_not_panic() {
+        let cluster = Cluster::for_topology_and_workload(topology(5), small_workload()).result;
+        // Losing `maintenance_headroom` nodes must still leave at least one node of capacity.
+        assert!(cluster.worker_node_count > cluster.topology.maintenance_headroom);
+    }
+
+    // migration_reserve_fraction (applied in resources()) and losing maintenance_headroom worker
+    // nodes (applied on top, in resources_after_losing_workers()) combine conservatively -- the
+    // lost nodes' share of the reserve is still charged against the remaining nodes, so this must not panic and
+    // must only ever report less memory than losing the same nodes without a reserve would.
+    #[test]
+    fn resources_after_losing_workers_combines_with_migration_reserve_without_panicking() {
+        let mut t = topology(2);
+        t.worker_node = node(64, 16);
+        let cluster = cluster_of(10, t.clone());
+        let without_reserve = cluster.resources_after_losing_workers(cluster.topology.maintenance_headroom).result;
+
+        t.migration_reserve_fraction = 0.1;
+        let cluster = cluster_of(10, t);
+        let with_reserve = cluster.resources_after_losing_workers(cluster.topology.maintenance_headroom).result;
+
+        assert!(with_reserve.available_to_workloads.memory.get_byte().get_bytes() < without_reserve.available_to_workloads.memory.get_byte().get_bytes());
+    }
+
+    // A worker node whose consumed_by_system overhead already exceeds its capacity (12Gi
+    // reserved, only 8Gi of capacity) can never yield non-zero allocatable resources no matter
+    // how many of them are added -- this must return a diagnostic immediately rather than
+    // growing worker_node_count forever.
+    #[test]
+    fn sizing_bails_out_when_worker_node_is_structurally_over_committed() {
+        let mut t = topology(0);
+        t.worker_node = Node {
+            description: "over-committed node".to_string(),
+            capacity: resources(8, 16),
+            consumed_by_system: resources(12, 0),
+            reserved_for_overhead: resources(0, 0)
+        };
+        let reasoned = Cluster::for_topology_and_workload(t, small_workload());
+        assert!(reasoned.reasons.iter().any(|r| r.contains("over-committed")));
+    }
+
+    fn instance_type(guest: Resources, no_overcommit: bool) -> InstanceType {
+        InstanceType {
+            name: "test-instance".to_string(),
+            guest,
+            consumed_by_system: resources(0, 0),
+            reserved_for_overhead: resources(0, 0),
+            no_overcommit
+        }
+    }
+
+    fn cluster_of(worker_node_count: u64, mut t: ClusterTopology) -> Cluster {
+        t.schedulable_control_plane = false;
+        Cluster { topology: t, control_plane_node_count: 0, worker_node_count }
+    }
+
+    // Aggregate capacity (20GiB across 2 nodes) is enough for 3 VMs of 6GiB each (18GiB), but no
+    // single node has 6GiB free once it already holds one, so the third VM can't be placed.
+    #[test]
+    fn place_reports_fragmentation_when_a_single_vm_cannot_fit_any_node() {
+        let mut t = topology(0);
+        t.worker_node = node(10, 4);
+        let cluster = cluster_of(2, t);
+        let workloads = Workloads { vm_count: 3, instance_type: instance_type(resources(6, 1), false) };
+        let reasoned = cluster.place(&workloads);
+        assert!(reasoned.reasons.iter().any(|r| r.contains("fragmentation across nodes")));
+        let placement = reasoned.result;
+        assert_eq!(placement.unplaced_count, 1);
+        assert_eq!(placement.placed_per_node.iter().sum::<u64>(), 2);
+    }
+
+    // A no_overcommit instanceType is charged against physical cores only; the over-committed
+    // vCPU pool (80 here) must not let a VM demanding more cores than physically exist be placed.
+    #[test]
+    fn place_respects_no_overcommit_physical_core_limit() {
+        let mut t = topology(0);
+        t.cpu_over_commit_ratio = 0.1;
+        t.worker_node = node(64, 8);
+        let cluster = cluster_of(1, t);
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(resources(1, 10), true) };
+        let placement = cluster.place(&workloads).result;
+        assert_eq!(placement.unplaced_count, 1);
+    }
+
+    // The same VM, without no_overcommit, draws from the over-committed vCPU pool (80 vCPUs from
+    // 8 physical cores at a 0.1 over-commit ratio) and fits even though it exceeds physical cores.
+    #[test]
+    fn place_allows_overcommitted_vcpu_demand_exceeding_physical_cores() {
+        let mut t = topology(0);
+        t.cpu_over_commit_ratio = 0.1;
+        t.worker_node = node(64, 8);
+        let cluster = cluster_of(1, t);
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(resources(1, 10), false) };
+        let placement = cluster.place(&workloads).result;
+        assert_eq!(placement.unplaced_count, 0);
+        assert_eq!(placement.placed_per_node, vec![1]);
+    }
+
+    // 5 worker nodes round-robined across 3 zones land as [2, 2, 1]; the largest zone (2 nodes)
+    // is what zone_redundancy must be able to lose without the workload falling over.
+    #[test]
+    fn worker_nodes_per_zone_distributes_round_robin() {
+        let mut t = topology(0);
+        t.zones = 3;
+        t.zone_redundancy = true;
+        let cluster = cluster_of(5, t);
+        assert_eq!(cluster.worker_nodes_per_zone(), vec![2, 2, 1]);
+        assert_eq!(cluster.largest_zone_worker_node_count(), 2);
+    }
+
+    // With zone redundancy, effective_resources() must reflect the cluster after losing the
+    // largest zone (2 of 5 nodes here), i.e. only 3 nodes' worth of memory remains.
+    #[test]
+    fn effective_resources_subtracts_the_largest_zone_when_zone_redundant() {
+        let mut t = topology(0);
+        t.zones = 3;
+        t.zone_redundancy = true;
+        t.worker_node = node(10, 4);
+        let cluster = cluster_of(5, t);
+        let effective = cluster.effective_resources().result;
+        assert_eq!(effective.available_to_workloads.memory.get_byte().get_bytes(), adjusted_from_bytes(3 * 10 * 1024 * 1024 * 1024).get_byte().get_bytes());
+    }
+
+    // The sizing loop itself (not a hand-built Cluster) must size a bigger cluster when zone
+    // redundancy is requested than without it, for the same topology and workload, since it now
+    // has to hold the workload even after losing the largest zone.
+    #[test]
+    fn sizing_loop_grows_the_cluster_more_with_zone_redundancy_than_without() {
+        let mut t = topology(0);
+        t.zones = 3;
+        t.worker_node = node(10, 4);
+        let workload = Workloads { vm_count: 20, instance_type: instance_type(resources(4, 2), false) };
+
+        t.zone_redundancy = false;
+        let without_redundancy = Cluster::for_topology_and_workload(t.clone(), workload.clone()).result;
+
+        t.zone_redundancy = true;
+        let with_redundancy = Cluster::for_topology_and_workload(t, workload).result;
+
+        assert!(with_redundancy.worker_node_count > without_redundancy.worker_node_count);
+    }
+
+    // Usable storage is raw per-node storage, summed across all worker nodes, divided by the
+    // replication factor -- here 3 nodes * 30GiB raw / 3x replication = 30GiB usable.
+    #[test]
+    fn resources_divides_storage_by_the_replica_factor() {
+        let mut t = topology(0);
+        t.storage_replica_factor = 3;
+        let mut worker = node(10, 4);
+        worker.capacity.storage = Some(adjusted_from_bytes(30 * 1024 * 1024 * 1024));
+        t.worker_node = worker;
+        let cluster = cluster_of(3, t);
+        let available = cluster.resources().result.available_to_workloads;
+        assert_eq!(available.storage.unwrap().get_byte().get_bytes(), adjusted_from_bytes(30 * 1024 * 1024 * 1024).get_byte().get_bytes());
+    }
+
+    // The replicated-storage model only replicates worker node storage; a schedulable control
+    // plane node's own storage capacity must not be silently folded into (and then discarded
+    // from) `available_to_workloads.storage`.
+    #[test]
+    fn resources_excludes_control_plane_storage_from_the_replicated_total() {
+        let mut t = topology(0);
+        t.schedulable_control_plane = true;
+        t.storage_replica_factor = 3;
+        let mut worker = node(10, 4);
+        worker.capacity.storage = Some(adjusted_from_bytes(30 * 1024 * 1024 * 1024));
+        t.worker_node = worker;
+        let mut ctl_node = node(16, 4);
+        ctl_node.capacity.storage = Some(adjusted_from_bytes(100 * 1024 * 1024 * 1024));
+        t.control_plane_node = ctl_node;
+        let cluster = Cluster { topology: t, control_plane_node_count: 3, worker_node_count: 3 };
+        let available = cluster.resources().result.available_to_workloads;
+        assert_eq!(available.storage.unwrap().get_byte().get_bytes(), adjusted_from_bytes(30 * 1024 * 1024 * 1024).get_byte().get_bytes());
+    }
+
+    #[test]
+    fn can_fit_into_rejects_a_workload_that_exceeds_available_storage() {
+        let mut avail = resources(100, 32);
+        avail.storage = Some(adjusted_from_bytes(10 * 1024 * 1024 * 1024));
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let mut guest = resources(4, 2);
+        guest.storage = Some(adjusted_from_bytes(20 * 1024 * 1024 * 1024));
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(guest, false) };
+        let result = workloads.can_fit_into(&resources_view);
+        assert!(!result.result);
+        assert_eq!(result.reasons, vec!["Constrained by storage".to_string()]);
+    }
+
+    // can_fit_into must charge the full resource_footprint (guest + consumed_by_system +
+    // reserved_for_overhead), the same demand place() checks -- otherwise a cluster sized as
+    // "fitting" the workload here would still fail to place every VM in place().
+    #[test]
+    fn can_fit_into_uses_the_full_footprint_not_just_guest_resources() {
+        let avail = resources(20, 8);
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let guest = resources(8, 2);
+        let mut it = instance_type(guest, false);
+        it.consumed_by_system = resources(2, 0);
+        it.reserved_for_overhead = resources(2, 0);
+        // Guest-only demand (2 * 8GiB = 16GiB) would fit the 20GiB node, but the full footprint
+        // (2 * 12GiB = 24GiB) does not.
+        let workloads = Workloads { vm_count: 2, instance_type: it };
+        let result = workloads.can_fit_into(&resources_view);
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn can_fit_into_rejects_a_workload_that_exceeds_available_iops() {
+        let mut avail = resources(100, 32);
+        avail.iops = Some(1000);
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let mut guest = resources(4, 2);
+        guest.iops = Some(2000);
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(guest, false) };
+        let result = workloads.can_fit_into(&resources_view);
+        assert!(!result.result);
+        assert_eq!(result.reasons, vec!["Constrained by IOPS".to_string()]);
+    }
+
+    // A non-no_overcommit instanceType's cpus demand is charged against the over-committed vCPU
+    // pool, not physical cores, so 20 "cpus" fits comfortably into 160 available vCPUs.
+    #[test]
+    fn can_fit_into_allows_vcpu_demand_within_the_overcommitted_pool() {
+        let mut avail = resources(100, 8);
+        avail.vcpus = Some(160);
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(resources(4, 20), false) };
+        let result = workloads.can_fit_into(&resources_view);
+        assert!(result.result);
+    }
+
+    // The same demand, with no_overcommit, is charged against physical cores (only 8 available)
+    // and must be rejected despite the vCPU pool having plenty of room.
+    #[test]
+    fn can_fit_into_rejects_no_overcommit_demand_exceeding_physical_cores() {
+        let mut avail = resources(100, 8);
+        avail.vcpus = Some(160);
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let workloads = Workloads { vm_count: 1, instance_type: instance_type(resources(4, 20), true) };
+        let result = workloads.can_fit_into(&resources_view);
+        assert!(!result.result);
+        assert_eq!(result.reasons, vec!["Constrained by physical CPU (no over-commit instanceType)".to_string()]);
+    }
+
+    // how_many_fit_into must route through the same vCPU-vs-physical-core branching as
+    // can_fit_into: a non-no_overcommit instanceType fits far more instances than the physical
+    // core count alone would allow, once the over-committed vCPU pool is taken into account.
+    #[test]
+    fn how_many_fit_into_uses_the_overcommitted_vcpu_pool() {
+        let mut avail = resources(1000, 8);
+        avail.vcpus = Some(160);
+        let resources_view = ClusterResources { consumed_by_system: resources(0, 0), reserved_for_overhead: resources(0, 0), available_to_workloads: avail };
+        let it = instance_type(resources(4, 20), false);
+        let (count, reason) = it.how_many_fit_into(&resources_view);
+        assert_eq!(count, 8);
+        assert_eq!(reason, "vCPU constraint");
+    }
+
+    // A topology JSON predating the zone/storage/SMT/headroom series has none of `zones`,
+    // `storage_replica_factor`, `threads_per_core` or `maintenance_headroom`; each must fall back
+    // to the value that reproduces the old, un-migrated behavior rather than failing to
+    // deserialize.
+    #[test]
+    fn cluster_topology_deserializes_pre_series_json_with_defaults() {
+        let json = r#"{
+            "schedulable_control_plane": false,
+            "control_plane_node": {
+                "description": "control",
+                "capacity": { "memory": 17179869184, "cpus": 4, "vcpus": null, "storage": null, "iops": null },
+                "consumed_by_system": { "memory": 0, "cpus": 0, "vcpus": null, "storage": null, "iops": null },
+                "reserved_for_overhead": { "memory": 0, "cpus": 0, "vcpus": null, "storage": null, "iops": null }
+            },
+            "worker_node": {
+                "description": "worker",
+                "capacity": { "memory": 68719476736, "cpus": 16, "vcpus": null, "storage": null, "iops": null },
+                "consumed_by_system": { "memory": 0, "cpus": 0, "vcpus": null, "storage": null, "iops": null },
+                "reserved_for_overhead": { "memory": 0, "cpus": 0, "vcpus": null, "storage": null, "iops": null }
+            },
+            "cpu_over_commit_ratio": 1.0
+        }"#;
+
+        let topology: ClusterTopology = serde_json::from_str(json).expect("pre-series JSON must still deserialize");
+        assert_eq!(topology.zones, 1);
+        assert_eq!(topology.zone_redundancy, false);
+        assert_eq!(topology.storage_replica_factor, 1);
+        assert_eq!(topology.threads_per_core, 1);
+        assert_eq!(topology.maintenance_headroom, 1);
+        assert_eq!(topology.migration_reserve_fraction, 0.0);
+    }
+}
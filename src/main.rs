@@ -133,7 +133,11 @@ fn main() {
                 let c: Cluster = load_from_file(&cmd.cluster_file).unwrap();
                 println!("Cluster: {}", c);
                 // Let's estimate the capacity of the cluster
-                println!("Estimated cluster capacity: {}", c.resources());
+                let reasoned_resources = c.resources();
+                println!("Estimated cluster capacity: {}", reasoned_resources.result);
+                if !reasoned_resources.reasons.is_empty() {
+                    println!("Reasoning:\n- {}", reasoned_resources.reasons.join("\n- "));
+                }
             } else {
                 // FIXME print help, how!?
                 todo!()
@@ -155,12 +159,23 @@ fn main() {
             println!("Workloads: {}", workload);
             println!("Workload resource footprint: {}", workload.required_resources());
 
-            let reasoned_cluster = Cluster::for_topology_and_workload(topology, workload);
+            let reasoned_cluster = Cluster::for_topology_and_workload(topology, workload.clone());
             let reasons = reasoned_cluster.reasons;
             let cluster = reasoned_cluster.result;
+            let reasoned_resources = cluster.resources();
+            let mut reasons = reasons;
+            reasons.extend(reasoned_resources.reasons);
             println!("Cluster: {}", &cluster);
-            println!("Cluster capacity: {}", &cluster.resources());
+            println!("Cluster capacity: {}", &reasoned_resources.result);
             println!("Reasoning:\n- {}", &reasons.join("\n- "));
+
+            // Aggregate totals overestimate capacity because a VM cannot straddle two nodes;
+            // confirm the sized cluster actually places every VM instance.
+            let reasoned_placement = cluster.place(&workload);
+            println!("Placement: {}", &reasoned_placement.result);
+            if !reasoned_placement.reasons.is_empty() {
+                println!("Placement reasoning:\n- {}", &reasoned_placement.reasons.join("\n- "));
+            }
         }
     }
 /*